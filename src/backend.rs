@@ -0,0 +1,100 @@
+//! Index and slice arithmetic shared by every place `Selector::Index`/`Slice`
+//! gets resolved against an array length.
+
+use std::cmp;
+
+/// Normalize a (possibly negative) `Selector::Index` against an array of the
+/// given `length`: negative indices count back from the end, everything else
+/// is used as-is.
+pub fn norm_index(index: i64, length: usize) -> usize {
+    if index < 0 && length >= index.unsigned_abs() as usize {
+        (length as i64 + index) as usize
+    } else {
+        index as usize
+    }
+}
+
+/// Compute the `(start, stop, step)` RFC 9535 slice bounds for an array of
+/// the given `length`, shared by every `Selector::Slice` resolution.
+pub fn slice_bounds(length: usize, start: Option<i64>, stop: Option<i64>, step: Option<i64>) -> SliceBounds {
+    let array_length = length as i64;
+    let n_step = step.unwrap_or(1);
+
+    if array_length == 0 || n_step == 0 {
+        return SliceBounds { start: 0, stop: 0, step: n_step };
+    }
+
+    let n_start = match start {
+        Some(i) => {
+            let i = if i < 0 { array_length + i } else { i };
+            if n_step < 0 {
+                cmp::min(cmp::max(i, -1), array_length - 1)
+            } else {
+                cmp::min(cmp::max(i, 0), array_length)
+            }
+        }
+        None => {
+            if n_step < 0 {
+                array_length - 1
+            } else {
+                0
+            }
+        }
+    };
+
+    let n_stop = match stop {
+        Some(i) => {
+            if i < 0 {
+                cmp::max(array_length + i, -1)
+            } else {
+                cmp::min(i, array_length)
+            }
+        }
+        None => {
+            if n_step < 0 {
+                -1
+            } else {
+                array_length
+            }
+        }
+    };
+
+    SliceBounds {
+        start: n_start,
+        stop: n_stop,
+        step: n_step,
+    }
+}
+
+/// Normalized slice bounds, ready to drive either a `step_by` forward walk or
+/// a manual reverse walk depending on the sign of `step`.
+#[derive(Debug, Clone, Copy)]
+pub struct SliceBounds {
+    pub start: i64,
+    pub stop: i64,
+    pub step: i64,
+}
+
+impl SliceBounds {
+    /// The sequence of array indices this slice selects, in order.
+    pub fn indices(&self) -> Vec<i64> {
+        let mut out = Vec::new();
+        if self.step == 0 {
+            return out;
+        }
+        if self.step > 0 {
+            let mut i = self.start;
+            while i < self.stop {
+                out.push(i);
+                i += self.step;
+            }
+        } else {
+            let mut i = self.start;
+            while i > self.stop {
+                out.push(i);
+                i += self.step;
+            }
+        }
+        out
+    }
+}
@@ -1,14 +1,42 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
 
-use crate::{JSONPathError, JSONPathParser, NodeList, Query};
+use crate::node::{Location, PathElement};
+use crate::{Extensions, JSONPathError, JSONPathErrorType, JSONPathParser, NodeList, Query};
 
 #[pyclass]
 pub struct Env {
     pub parser: JSONPathParser,
     pub function_register: Py<PyDict>,
     pub nothing: PyObject,
+    /// When `true`, `compile`/`find` reorder the operands of `&&`/`||`
+    /// chains in filter expressions cheapest-first, so short-circuit
+    /// evaluation does as little work as possible. Defaults to `false`
+    /// (strict left-to-right evaluation order): reordering is an observable
+    /// change, not just a perf tweak — a function extension can error on
+    /// some inputs (see `JSONPathExtensionError`), and which operand runs
+    /// first decides whether `&&`/`||` short-circuits past that error or
+    /// hits it. Enable only once callers don't depend on evaluation order,
+    /// e.g. because their function extensions are pure and total.
+    pub reorder_filters: bool,
+    /// Host-supplied state reachable from `QueryContext::extensions` during
+    /// resolution (see `crate::extensions::Extensions`). Shared via `Arc`
+    /// rather than cloned per query, since it's set once for the `Env`'s
+    /// whole lifetime; `Arc` rather than `Rc` because `#[pyclass]` types must
+    /// be `Send`.
+    pub extensions: Arc<Extensions>,
+    /// The deepest `Segment::Recursive` is allowed to descend before
+    /// resolution aborts with a `JSONPathLimitError`, or `None` (the
+    /// default) for unbounded descent. A DoS backstop for `$..` queries
+    /// over untrusted, deeply nested documents.
+    pub max_depth: Option<usize>,
+    /// The most nodes a single segment is allowed to produce before
+    /// resolution aborts with a `JSONPathLimitError`, or `None` (the
+    /// default) for unbounded results. A DoS backstop for queries that would
+    /// otherwise materialize huge `NodeList`s from untrusted documents.
+    pub max_results: Option<usize>,
 }
 
 #[pymethods]
@@ -71,19 +99,36 @@ impl Env {
             parser,
             function_register: function_register.clone().unbind(),
             nothing: nothing.clone().unbind(),
+            reorder_filters: option_reorder_filters(options)?,
+            extensions: Arc::new(build_extensions(options)?),
+            max_depth: option_max_depth(options)?,
+            max_results: option_max_results(options)?,
         })
     }
 
+    /// The `extensions` option passed to the constructor, if any, so host
+    /// code can read back what it bound without keeping its own reference to
+    /// the original dict around.
+    #[getter]
+    fn extensions(&self, py: Python<'_>) -> Option<Py<PyDict>> {
+        self.extensions.get::<Py<PyDict>>().map(|d| d.clone_ref(py))
+    }
+
     pub fn find<'py>(
         &self,
         query: &str,
         value: &Bound<'py, PyAny>,
     ) -> Result<NodeList<'py>, JSONPathError> {
-        self.parser.parse(query)?.resolve(value, self)
+        self.compile(query)?.resolve(value, self)
     }
 
     pub fn compile(&self, query: &str) -> Result<Query, JSONPathError> {
-        self.parser.parse(query)
+        let query = self.parser.parse(query)?;
+        if self.reorder_filters {
+            Ok(query.reordered())
+        } else {
+            Ok(query)
+        }
     }
 
     pub fn query<'py>(
@@ -93,6 +138,129 @@ impl Env {
     ) -> Result<NodeList<'py>, JSONPathError> {
         query.resolve(value, self)
     }
+
+    /// Like `find`, but returns each match's RFC 9535 normalized path
+    /// instead of its value.
+    pub fn find_paths(&self, query: &str, value: &Bound<'_, PyAny>) -> Result<Vec<String>, JSONPathError> {
+        Ok(self
+            .find(query, value)?
+            .into_iter()
+            .map(|node| node.path())
+            .collect())
+    }
+
+    /// Replace every node matched by `query` with the return value of calling
+    /// `callback` with the node's current value, mutating `value` in place.
+    ///
+    /// A match on the query root itself (`$`) has no parent container to
+    /// reassign it in, so it's silently skipped rather than replaced.
+    pub fn update<'py>(
+        &self,
+        query: &str,
+        value: &Bound<'py, PyAny>,
+        callback: &Bound<'py, PyAny>,
+    ) -> Result<(), JSONPathError> {
+        let nodes = self.find(query, value)?;
+
+        for node in nodes {
+            let Some((parent, key)) = navigate_to_parent(value, &node.location) else {
+                continue;
+            };
+
+            let new_value = callback.call1((node.value.bind(value.py()),)).map_err(|err| {
+                JSONPathError::new(JSONPathErrorType::ExtError, err.to_string())
+            })?;
+
+            let result = match key {
+                PathElement::Index(i) => parent.set_item(i, new_value),
+                PathElement::Name(name) => parent.set_item(name, new_value),
+            };
+
+            result.map_err(|err| JSONPathError::new(JSONPathErrorType::ExtError, err.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove every node matched by `query` from `value`, mutating it in place.
+    ///
+    /// List elements are removed in descending index order, per parent list, so
+    /// that earlier removals don't shift the indices of later matches. A match
+    /// on the query root itself (`$`) has no parent container to remove it
+    /// from, so it's silently skipped rather than deleted.
+    pub fn delete<'py>(&self, query: &str, value: &Bound<'py, PyAny>) -> Result<(), JSONPathError> {
+        let nodes = self.find(query, value)?;
+
+        // Keyed by the parent list's identity so each list's matched indices can
+        // be deleted together, highest index first.
+        let mut list_deletions: HashMap<usize, (Bound<'py, PyAny>, Vec<i64>)> = HashMap::new();
+        // Keyed by (parent dict's identity, member name), so a name matched more
+        // than once by the same query (e.g. two filter selectors in one segment
+        // both selecting it) is only deleted once instead of raising on the
+        // second `del_item` once the first has already removed it.
+        let mut dict_deletions: HashMap<(usize, String), Bound<'py, PyAny>> = HashMap::new();
+
+        for node in &nodes {
+            let Some((parent, key)) = navigate_to_parent(value, &node.location) else {
+                continue;
+            };
+
+            match key {
+                PathElement::Index(i) => {
+                    list_deletions
+                        .entry(parent.as_ptr() as usize)
+                        .or_insert_with(|| (parent.clone(), Vec::new()))
+                        .1
+                        .push(i as i64);
+                }
+                PathElement::Name(name) => {
+                    dict_deletions
+                        .entry((parent.as_ptr() as usize, name))
+                        .or_insert(parent);
+                }
+            }
+        }
+
+        for (_, (parent, mut indices)) in list_deletions {
+            indices.sort_unstable_by(|a, b| b.cmp(a));
+            indices.dedup();
+            for index in indices {
+                parent
+                    .del_item(index)
+                    .map_err(|err| JSONPathError::new(JSONPathErrorType::ExtError, err.to_string()))?;
+            }
+        }
+
+        for ((_, name), parent) in dict_deletions {
+            parent
+                .del_item(name)
+                .map_err(|err| JSONPathError::new(JSONPathErrorType::ExtError, err.to_string()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Walk `location` from the root down to the parent container of the node it
+/// addresses, returning that parent along with the final path element (the
+/// key or index that selects the node from it).
+fn navigate_to_parent<'py>(
+    root: &Bound<'py, PyAny>,
+    location: &Location,
+) -> Option<(Bound<'py, PyAny>, PathElement)> {
+    let mut elements: Vec<PathElement> = location.iter().cloned().collect();
+    elements.reverse();
+    let key = elements.pop()?;
+
+    let mut current = root.clone();
+    for element in elements {
+        current = match element {
+            PathElement::Index(i) => current.get_item(i).ok()?,
+            PathElement::Name(ref name) => current.get_item(name).ok()?,
+        };
+    }
+
+    Some((current, key))
 }
 
 fn option_strict(options: Option<&Bound<'_, PyDict>>) -> PyResult<bool> {
@@ -104,3 +272,52 @@ fn option_strict(options: Option<&Bound<'_, PyDict>>) -> PyResult<bool> {
             .unwrap_or(Ok(true))?),
     }
 }
+
+fn option_reorder_filters(options: Option<&Bound<'_, PyDict>>) -> PyResult<bool> {
+    match options {
+        None => Ok(false),
+        Some(py_dict) => Ok(py_dict
+            .get_item("reorder_filters")?
+            .map(|val| val.is_truthy())
+            .unwrap_or(Ok(false))?),
+    }
+}
+
+fn option_max_depth(options: Option<&Bound<'_, PyDict>>) -> PyResult<Option<usize>> {
+    match options {
+        None => Ok(None),
+        Some(py_dict) => py_dict
+            .get_item("max_depth")?
+            .map(|val| val.extract::<usize>())
+            .transpose(),
+    }
+}
+
+fn option_max_results(options: Option<&Bound<'_, PyDict>>) -> PyResult<Option<usize>> {
+    match options {
+        None => Ok(None),
+        Some(py_dict) => py_dict
+            .get_item("max_results")?
+            .map(|val| val.extract::<usize>())
+            .transpose(),
+    }
+}
+
+/// Build this `Env`'s `Extensions` store from its constructor options. A
+/// dict passed as the `extensions` option is inserted as-is, keyed by its
+/// Rust type (`Py<PyDict>`); custom function extensions and selectors that
+/// know to look for it can then read it back via `QueryContext::extensions`.
+fn build_extensions(options: Option<&Bound<'_, PyDict>>) -> PyResult<Extensions> {
+    let mut extensions = Extensions::new();
+
+    if let Some(py_dict) = options {
+        if let Some(user_data) = py_dict.get_item("extensions")? {
+            let user_data: Py<PyDict> = user_data
+                .extract()
+                .map_err(|_| PyValueError::new_err("expected `extensions` option to be a dict"))?;
+            extensions.insert(user_data);
+        }
+    }
+
+    Ok(extensions)
+}
@@ -11,23 +11,47 @@ pub enum JSONPathErrorType {
     TypeError,
     NameError,
     ExtError,
+    LimitError,
 }
 
 #[derive(Debug)]
 pub struct JSONPathError {
     pub kind: JSONPathErrorType,
     pub msg: String,
+    /// The `(start, length)` byte span of the offending token in the
+    /// original query string, when known. Set via `with_span` by
+    /// `JSONPathParser::parse` as it lexes/parses a query; `None` for errors
+    /// raised outside of that context.
+    pub span: Option<(usize, usize)>,
+    /// The original query string, kept alongside `span` so the error can
+    /// render its own annotated snippet without the caller having to pass
+    /// the query back in.
+    pub query: Option<String>,
+    /// The RFC 9535 normalized path of the node being resolved when this
+    /// error was raised, set via `with_path` for evaluation-time errors (e.g.
+    /// a `max_depth`/`max_results` limit) that have no source span to point
+    /// at.
+    pub path: Option<String>,
 }
 
 impl JSONPathError {
     pub fn new(error: JSONPathErrorType, msg: String) -> Self {
-        Self { kind: error, msg }
+        Self {
+            kind: error,
+            msg,
+            span: None,
+            query: None,
+            path: None,
+        }
     }
 
     pub fn syntax(msg: String) -> Self {
         Self {
             kind: JSONPathErrorType::SyntaxError,
             msg,
+            span: None,
+            query: None,
+            path: None,
         }
     }
 
@@ -35,6 +59,9 @@ impl JSONPathError {
         Self {
             kind: JSONPathErrorType::TypeError,
             msg,
+            span: None,
+            query: None,
+            path: None,
         }
     }
 
@@ -42,6 +69,9 @@ impl JSONPathError {
         Self {
             kind: JSONPathErrorType::NameError,
             msg,
+            span: None,
+            query: None,
+            path: None,
         }
     }
 
@@ -49,8 +79,69 @@ impl JSONPathError {
         Self {
             kind: JSONPathErrorType::ExtError,
             msg,
+            span: None,
+            query: None,
+            path: None,
+        }
+    }
+
+    /// An error raised when evaluation hits a configured `Env` guard — its
+    /// `max_depth` or `max_results` limit — rather than a problem with the
+    /// query or the argument itself.
+    pub fn limit(msg: String) -> Self {
+        Self {
+            kind: JSONPathErrorType::LimitError,
+            msg,
+            span: None,
+            query: None,
+            path: None,
         }
     }
+
+    /// Attach the source span of the offending token so this error can
+    /// render a caret-underlined diagnostic. Call `in_query` as well to get
+    /// the full annotated snippet; without it, `annotated` falls back to
+    /// reporting the span as a byte range.
+    pub fn with_span(mut self, span: (usize, usize)) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Attach the original query text, so `annotated` can render the
+    /// offending line alongside this error's span.
+    pub fn in_query(mut self, query: &str) -> Self {
+        self.query = Some(query.to_owned());
+        self
+    }
+
+    /// Attach the normalized path of the node being resolved when this error
+    /// was raised, so `annotated` can name it in place of a source span.
+    pub fn with_path(mut self, path: String) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Render `self.msg` followed by the offending line of `self.query` with
+    /// a caret underline beneath `self.span`. Without a query, the span is
+    /// reported as a byte range; without a span, `self.msg` is followed by
+    /// `self.path` if one was attached, or returned alone otherwise.
+    pub fn annotated(&self) -> String {
+        let Some((start, len)) = self.span else {
+            return match &self.path {
+                Some(path) => format!("{} (at {})", self.msg, path),
+                None => self.msg.clone(),
+            };
+        };
+
+        let Some(query) = &self.query else {
+            return format!("{} (at byte {}..{})", self.msg, start, start + len);
+        };
+
+        let underline_len = len.max(1);
+        let caret_line = format!("{}{}", " ".repeat(start), "^".repeat(underline_len));
+
+        format!("{}\n    {}\n    {}", self.msg, query, caret_line)
+    }
 }
 
 impl std::error::Error for JSONPathError {}
@@ -90,22 +181,46 @@ create_exception!(
     "JSONPath function extension error."
 );
 
+create_exception!(
+    jpq,
+    JSONPathLimitError,
+    PyJSONPathError,
+    "A configured `Env` evaluation limit (`max_depth` or `max_results`) was exceeded."
+);
+
 impl std::convert::From<JSONPathError> for PyErr {
     fn from(err: JSONPathError) -> Self {
         use JSONPathErrorType::*;
-        match err.kind {
-            // TODO: improve error messages
-            TypeError => JSONPathTypeError::new_err(err.to_string()),
-            SyntaxError => JSONPathSyntaxError::new_err(err.to_string()),
-            NameError => JSONPathNameError::new_err(err.to_string()),
-            ExtError => JSONPathExtensionError::new_err(err.to_string()),
-            _ => PyJSONPathError::new_err(err.to_string()),
+        let span = err.span;
+        let path = err.path.clone();
+        let rendered = err.to_string();
+        let py_err = match err.kind {
+            TypeError => JSONPathTypeError::new_err(rendered),
+            SyntaxError => JSONPathSyntaxError::new_err(rendered),
+            NameError => JSONPathNameError::new_err(rendered),
+            ExtError => JSONPathExtensionError::new_err(rendered),
+            LimitError => JSONPathLimitError::new_err(rendered),
+            _ => PyJSONPathError::new_err(rendered),
+        };
+
+        if let Some((start, len)) = span {
+            Python::with_gil(|py| {
+                let _ = py_err.value_bound(py).setattr("span", (start, len));
+            });
         }
+
+        if let Some(path) = path {
+            Python::with_gil(|py| {
+                let _ = py_err.value_bound(py).setattr("path", path);
+            });
+        }
+
+        py_err
     }
 }
 
 impl fmt::Display for JSONPathError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.msg)
+        write!(f, "{}", self.annotated())
     }
 }
@@ -0,0 +1,31 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// A type-keyed bag of host-supplied state, modeled on the "context data"
+/// pattern used by async-graphql: a caller `insert::<T>(value)`s once per
+/// environment, and code that resolves a query later — a custom selector, a
+/// function extension — `get::<T>()`s it back out, without a new parameter
+/// threaded through every method between the two.
+///
+/// Typical uses are a locale for string comparisons, a clock for
+/// `now()`-style function extensions, or a handle to a caching layer.
+#[derive(Default)]
+pub struct Extensions {
+    data: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        self.data.insert(TypeId::of::<T>(), Box::new(value));
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.data
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref::<T>())
+    }
+}
@@ -4,48 +4,398 @@ use pyo3::prelude::*;
 use pyo3::types::{PyBool, PyFloat, PyInt, PyNone, PyString, PyTuple};
 
 use crate::node::Value;
-use crate::{ExpressionType, NodeList, Query, QueryContext};
+use crate::{ExpressionType, JSONPathError, NodeList, Query, QueryContext};
 
 #[pyclass]
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FilterExpression {
-    True_ {},
-    False_ {},
-    Null {},
+    True_ {
+        span: (usize, usize),
+    },
+    False_ {
+        span: (usize, usize),
+    },
+    Null {
+        span: (usize, usize),
+    },
     StringLiteral {
         value: String,
+        span: (usize, usize),
     },
     Int {
         value: i64,
+        span: (usize, usize),
     },
     Float {
         value: f64,
+        span: (usize, usize),
     },
     Not {
         expression: Box<FilterExpression>,
+        span: (usize, usize),
     },
     Logical {
         left: Box<FilterExpression>,
         operator: LogicalOperator,
         right: Box<FilterExpression>,
+        span: (usize, usize),
     },
     Comparison {
         left: Box<FilterExpression>,
         operator: ComparisonOperator,
         right: Box<FilterExpression>,
+        span: (usize, usize),
     },
     RelativeQuery {
         query: Box<Query>,
+        span: (usize, usize),
     },
     RootQuery {
         query: Box<Query>,
+        span: (usize, usize),
     },
     Function {
         name: String,
         args: Vec<FilterExpression>,
+        span: (usize, usize),
     },
 }
 
+impl FilterExpression {
+    /// The span of source text this node was parsed from.
+    pub fn span(&self) -> (usize, usize) {
+        use FilterExpression::*;
+        match self {
+            True_ { span }
+            | False_ { span }
+            | Null { span }
+            | StringLiteral { span, .. }
+            | Int { span, .. }
+            | Float { span, .. }
+            | Not { span, .. }
+            | Logical { span, .. }
+            | Comparison { span, .. }
+            | RelativeQuery { span, .. }
+            | RootQuery { span, .. }
+            | Function { span, .. } => *span,
+        }
+    }
+
+    /// Return an equivalent expression with `&&`/`||` chains reordered so
+    /// that, e.g., `a && b` and `b && a` compare equal. This is a distinct,
+    /// opt-in step: plain `==`/`Hash` treat logical operands positionally,
+    /// since `&&`/`||` aren't commutative from the caller's point of view
+    /// unless they explicitly ask for this normalization.
+    pub fn canonicalized(&self) -> FilterExpression {
+        use FilterExpression::*;
+        match self {
+            Not { expression, span } => Not {
+                expression: Box::new(expression.canonicalized()),
+                span: *span,
+            },
+            Logical {
+                left,
+                operator,
+                right,
+                span,
+            } => {
+                let mut left = Box::new(left.canonicalized());
+                let mut right = Box::new(right.canonicalized());
+                if left.to_string() > right.to_string() {
+                    std::mem::swap(&mut left, &mut right);
+                }
+                Logical {
+                    left,
+                    operator: operator.clone(),
+                    right,
+                    span: *span,
+                }
+            }
+            Comparison {
+                left,
+                operator,
+                right,
+                span,
+            } => Comparison {
+                left: Box::new(left.canonicalized()),
+                operator: operator.clone(),
+                right: Box::new(right.canonicalized()),
+                span: *span,
+            },
+            Function { name, args, span } => Function {
+                name: name.clone(),
+                args: args.iter().map(FilterExpression::canonicalized).collect(),
+                span: *span,
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// A rough, relative cost of evaluating this expression: 0 for literals,
+    /// small for comparisons between them, large for anything that has to
+    /// walk the document (`@...`/`$...`) or call into Python (a function
+    /// extension). Used to put cheap predicates first in a `&&`/`||` chain.
+    pub fn cost(&self) -> u32 {
+        use FilterExpression::*;
+        match self {
+            True_ { .. } | False_ { .. } | Null { .. } | StringLiteral { .. } | Int { .. }
+            | Float { .. } => 0,
+            Not { expression, .. } => 1 + expression.cost(),
+            Comparison { left, right, .. } => 1 + left.cost() + right.cost(),
+            Logical { left, right, .. } => left.cost() + right.cost(),
+            RelativeQuery { .. } | RootQuery { .. } => 10,
+            Function { args, .. } => 10 + args.iter().map(FilterExpression::cost).sum::<u32>(),
+        }
+    }
+
+    /// Reorder the operands of `&&`/`||` chains so the cheapest (by `cost`)
+    /// subexpression is evaluated first, recursing into children. A run of
+    /// nested `Logical` nodes that share the same operator — e.g.
+    /// `a && b && c` — is flattened and sorted as one chain rather than only
+    /// swapping each node's two direct operands, so a cheap operand can hoist
+    /// past more than one expensive one. The boolean result of a chain is
+    /// unchanged, but which operands get evaluated is not: a function
+    /// extension that errors is reached or skipped depending on where it ends
+    /// up, so callers that want strict left-to-right evaluation order should
+    /// skip calling this.
+    pub fn reordered(&self) -> FilterExpression {
+        use FilterExpression::*;
+        match self {
+            Not { expression, span } => Not {
+                expression: Box::new(expression.reordered()),
+                span: *span,
+            },
+            Logical {
+                left,
+                operator,
+                right,
+                span,
+            } => {
+                let mut operands = Vec::new();
+                flatten_chain(left, operator, &mut operands);
+                flatten_chain(right, operator, &mut operands);
+                operands.sort_by_key(FilterExpression::cost);
+                rebuild_chain(operands, operator, *span)
+            }
+            Comparison {
+                left,
+                operator,
+                right,
+                span,
+            } => Comparison {
+                left: Box::new(left.reordered()),
+                operator: operator.clone(),
+                right: Box::new(right.reordered()),
+                span: *span,
+            },
+            Function { name, args, span } => Function {
+                name: name.clone(),
+                args: args.iter().map(FilterExpression::reordered).collect(),
+                span: *span,
+            },
+            other => other.clone(),
+        }
+    }
+}
+
+/// Collect `expr`'s operands into `out`, splicing through nested `Logical`
+/// nodes that share `operator` so a whole `&&`/`||` run flattens into one
+/// chain instead of each node only seeing its own two direct operands. A
+/// nested `Logical` with a *different* operator is reordered internally and
+/// pushed as a single operand, since hoisting an operand past an operator
+/// change would alter which subexpressions short-circuit together.
+fn flatten_chain(expr: &FilterExpression, operator: &LogicalOperator, out: &mut Vec<FilterExpression>) {
+    if let FilterExpression::Logical { left, operator: op, right, .. } = expr {
+        if op == operator {
+            flatten_chain(left, operator, out);
+            flatten_chain(right, operator, out);
+            return;
+        }
+    }
+    out.push(expr.reordered());
+}
+
+/// Rebuild a cheapest-first `&&`/`||` chain from `operands` (as flattened and
+/// sorted by `FilterExpression::reordered`), left-associating pairwise.
+/// `span` is reused for every synthetic `Logical` node the rebuild
+/// introduces, since none of them were actually parsed from source text.
+fn rebuild_chain(
+    mut operands: Vec<FilterExpression>,
+    operator: &LogicalOperator,
+    span: (usize, usize),
+) -> FilterExpression {
+    let mut chain = operands.remove(0);
+    for operand in operands {
+        chain = FilterExpression::Logical {
+            left: Box::new(chain),
+            operator: operator.clone(),
+            right: Box::new(operand),
+            span,
+        };
+    }
+    chain
+}
+
+impl PartialEq for FilterExpression {
+    /// Structural equality, ignoring source spans: two expressions are equal
+    /// if they have the same shape and the same literals/operators/queries,
+    /// regardless of where (or whether) they were parsed from. Logical
+    /// operands are compared positionally — `a && b` is *not* equal to
+    /// `b && a` here; use `canonicalized()` first if that's wanted.
+    fn eq(&self, other: &Self) -> bool {
+        use FilterExpression::*;
+        match (self, other) {
+            (True_ { .. }, True_ { .. }) => true,
+            (False_ { .. }, False_ { .. }) => true,
+            (Null { .. }, Null { .. }) => true,
+            (StringLiteral { value: a, .. }, StringLiteral { value: b, .. }) => a == b,
+            (Int { value: a, .. }, Int { value: b, .. }) => a == b,
+            (Float { value: a, .. }, Float { value: b, .. }) => a.to_bits() == b.to_bits(),
+            (Not { expression: a, .. }, Not { expression: b, .. }) => a == b,
+            (
+                Logical {
+                    left: al,
+                    operator: ao,
+                    right: ar,
+                    ..
+                },
+                Logical {
+                    left: bl,
+                    operator: bo,
+                    right: br,
+                    ..
+                },
+            ) => ao == bo && al == bl && ar == br,
+            (
+                Comparison {
+                    left: al,
+                    operator: ao,
+                    right: ar,
+                    ..
+                },
+                Comparison {
+                    left: bl,
+                    operator: bo,
+                    right: br,
+                    ..
+                },
+            ) => ao == bo && al == bl && ar == br,
+            (RelativeQuery { query: a, .. }, RelativeQuery { query: b, .. }) => a == b,
+            (RootQuery { query: a, .. }, RootQuery { query: b, .. }) => a == b,
+            (Function { name: an, args: aa, .. }, Function { name: bn, args: ba, .. }) => {
+                an == bn && aa == ba
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for FilterExpression {}
+
+impl std::hash::Hash for FilterExpression {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        use FilterExpression::*;
+        std::mem::discriminant(self).hash(state);
+        match self {
+            True_ { .. } | False_ { .. } | Null { .. } => {}
+            StringLiteral { value, .. } => value.hash(state),
+            Int { value, .. } => value.hash(state),
+            Float { value, .. } => value.to_bits().hash(state),
+            Not { expression, .. } => expression.hash(state),
+            Logical {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                left.hash(state);
+                operator.hash(state);
+                right.hash(state);
+            }
+            Comparison {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                left.hash(state);
+                operator.hash(state);
+                right.hash(state);
+            }
+            RelativeQuery { query, .. } => query.hash(state),
+            RootQuery { query, .. } => query.hash(state),
+            Function { name, args, .. } => {
+                name.hash(state);
+                args.hash(state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    fn hash_of(expr: &FilterExpression) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        expr.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn int_literals_with_different_spans_are_equal() {
+        let a = FilterExpression::Int { value: 1, span: (0, 1) };
+        let b = FilterExpression::Int { value: 1, span: (9, 20) };
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+    }
+
+    #[test]
+    fn int_literals_with_different_values_are_not_equal() {
+        let a = FilterExpression::Int { value: 1, span: (0, 1) };
+        let b = FilterExpression::Int { value: 2, span: (0, 1) };
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn logical_expressions_with_different_spans_are_equal() {
+        let a = FilterExpression::Logical {
+            left: Box::new(FilterExpression::True_ { span: (0, 1) }),
+            operator: LogicalOperator::And,
+            right: Box::new(FilterExpression::False_ { span: (2, 3) }),
+            span: (0, 3),
+        };
+        let b = FilterExpression::Logical {
+            left: Box::new(FilterExpression::True_ { span: (100, 101) }),
+            operator: LogicalOperator::And,
+            right: Box::new(FilterExpression::False_ { span: (102, 103) }),
+            span: (100, 103),
+        };
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+    }
+
+    #[test]
+    fn logical_expressions_are_compared_positionally_not_commutatively() {
+        let a = FilterExpression::Logical {
+            left: Box::new(FilterExpression::True_ { span: (0, 1) }),
+            operator: LogicalOperator::And,
+            right: Box::new(FilterExpression::False_ { span: (2, 3) }),
+            span: (0, 3),
+        };
+        let b = FilterExpression::Logical {
+            left: Box::new(FilterExpression::False_ { span: (0, 1) }),
+            operator: LogicalOperator::And,
+            right: Box::new(FilterExpression::True_ { span: (2, 3) }),
+            span: (0, 3),
+        };
+        assert_ne!(a, b);
+    }
+}
+
 impl<'py> pyo3::FromPyObject<'py> for Box<FilterExpression> {
     fn extract(ob: &'py PyAny) -> PyResult<Self> {
         ob.extract::<FilterExpression>().map(Box::new)
@@ -59,7 +409,8 @@ impl pyo3::IntoPy<pyo3::PyObject> for Box<FilterExpression> {
 }
 
 #[pyclass]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LogicalOperator {
     And,
     Or,
@@ -89,7 +440,8 @@ impl LogicalOperator {
 }
 
 #[pyclass]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ComparisonOperator {
     Eq,
     Ne,
@@ -160,11 +512,11 @@ impl FilterExpression {
         &self,
         current: &Value<'py>,
         context: &QueryContext<'py>,
-    ) -> FilterExpressionResult<'py> {
+    ) -> Result<FilterExpressionResult<'py>, JSONPathError> {
         use FilterExpression::*;
         use FilterExpressionResult::*;
         let py = context.root.py();
-        match self {
+        let result = match self {
             True_ { .. } => any_bool!(py, true),
             False_ { .. } => any_bool!(py, false),
             Null { .. } => Object(PyNone::get_bound(py).as_any().to_owned()),
@@ -174,21 +526,30 @@ impl FilterExpression {
             Int { value, .. } => Object(value.to_object(py).bind(py).to_owned()),
             Float { value, .. } => Object(value.to_object(py).bind(py).to_owned()),
             Not { expression, .. } => {
-                any_bool!(py, !is_truthy(&expression.evaluate(current, context)))
+                any_bool!(py, !is_truthy(&expression.evaluate(current, context)?))
             }
             Logical {
                 left,
                 operator,
                 right,
                 ..
-            } => any_bool!(
-                py,
-                logical(
-                    &left.evaluate(current, context),
-                    operator,
-                    &right.evaluate(current, context),
-                )
-            ),
+            } => {
+                // Filter expressions are pure (RFC 9535 has no side
+                // effects), so short-circuiting changes nothing but how much
+                // work gets done: `&&` skips `right` once `left` is false,
+                // `||` skips it once `left` is true.
+                let left_truthy = is_truthy(&left.evaluate(current, context)?);
+                let short_circuits = match operator {
+                    LogicalOperator::And => !left_truthy,
+                    LogicalOperator::Or => left_truthy,
+                };
+
+                if short_circuits {
+                    any_bool!(py, left_truthy)
+                } else {
+                    any_bool!(py, is_truthy(&right.evaluate(current, context)?))
+                }
+            }
             Comparison {
                 left,
                 operator,
@@ -196,9 +557,9 @@ impl FilterExpression {
                 ..
             } => {
                 if compare(
-                    left.evaluate(current, context),
+                    left.evaluate(current, context)?,
                     operator,
-                    right.evaluate(current, context),
+                    right.evaluate(current, context)?,
                     py,
                 ) {
                     any_bool!(py, true)
@@ -206,52 +567,56 @@ impl FilterExpression {
                     any_bool!(py, false)
                 }
             }
-            RelativeQuery { query, .. } => Nodes(query.resolve(current, context.env)),
-            RootQuery { query, .. } => Nodes(query.resolve(&context.root, context.env)),
-            Function { name, args } => {
+            RelativeQuery { query, .. } => Nodes(query.resolve(current, context.env)?),
+            RootQuery { query, .. } => Nodes(query.resolve(&context.root, context.env)?),
+            Function {
+                name, args, span, ..
+            } => {
                 let obj = context
                     .env
                     .function_register
                     .bind(py)
                     .get_item(name)
-                    .unwrap_or_else(|_| panic!("missing function definition for {}", name))
-                    .unwrap_or_else(|| panic!("missing function definition for {}", name));
+                    .ok()
+                    .flatten()
+                    .ok_or_else(|| {
+                        self.ext_error(*span, format!("missing function definition for '{name}'"))
+                    })?;
 
                 let sig = context
                     .env
                     .parser
                     .function_signatures
                     .get(name)
-                    .unwrap_or_else(|| panic!("missing function signature for {}", name));
-
-                let _args: Vec<Value> = args
-                    .iter()
-                    .map(|ex| ex.evaluate(current, context))
-                    .enumerate()
-                    .map(|(i, rv)| {
-                        unpack_result(
-                            rv,
-                            &sig.param_types,
-                            i,
-                            context.env.nothing.clone().bind(py),
-                            py,
-                        )
-                    })
-                    .collect();
+                    .ok_or_else(|| {
+                        self.ext_error(*span, format!("missing function signature for '{name}'"))
+                    })?;
+
+                let mut _args: Vec<Value> = Vec::with_capacity(args.len());
+                for (i, ex) in args.iter().enumerate() {
+                    let rv = ex.evaluate(current, context)?;
+                    _args.push(unpack_result(
+                        rv,
+                        &sig.param_types,
+                        i,
+                        context.env.nothing.clone().bind(py),
+                        py,
+                    ));
+                }
 
-                let rv = obj
-                    .call1(PyTuple::new_bound(py, _args))
-                    .unwrap_or_else(|_| {
-                        panic!("unexpected error in function extension '{}'", name)
-                    });
+                let rv = obj.call1(PyTuple::new_bound(py, _args)).map_err(|_| {
+                    self.ext_error(*span, format!("unexpected error in function extension '{name}'"))
+                })?;
 
                 match sig.return_type {
-                    ExpressionType::Nodes => Nodes(rv.extract().unwrap_or_else(|_| {
-                        panic!(
-                            "expected a NodesType return value from function extension '{}'",
-                            name
+                    ExpressionType::Nodes => Nodes(rv.extract().map_err(|_| {
+                        self.ext_error(
+                            *span,
+                            format!(
+                                "expected a NodesType return value from function extension '{name}'"
+                            ),
                         )
-                    })),
+                    })?),
                     _ => {
                         if rv.eq(context.env.nothing.clone()).unwrap() {
                             Nothing
@@ -261,7 +626,15 @@ impl FilterExpression {
                     }
                 }
             }
-        }
+        };
+
+        Ok(result)
+    }
+
+    /// Build an `ExtError` carrying `span`, for evaluation-time failures
+    /// that used to `panic!`.
+    fn ext_error(&self, span: (usize, usize), msg: String) -> JSONPathError {
+        JSONPathError::ext(msg).with_span(span)
     }
 }
 
@@ -407,17 +780,6 @@ pub fn is_truthy(rv: &FilterExpressionResult) -> bool {
     }
 }
 
-fn logical(
-    left: &FilterExpressionResult,
-    op: &LogicalOperator,
-    right: &FilterExpressionResult,
-) -> bool {
-    match op {
-        LogicalOperator::And => is_truthy(left) && is_truthy(right),
-        LogicalOperator::Or => is_truthy(left) || is_truthy(right),
-    }
-}
-
 fn nodes_or_singular<'py>(
     rv: FilterExpressionResult<'py>,
     py: Python<'py>,
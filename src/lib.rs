@@ -1,18 +1,23 @@
+pub mod backend;
 mod conslist;
 pub mod environment;
 pub mod errors;
+pub mod extensions;
 pub mod filter;
 mod node;
 pub mod parser;
 pub mod query;
 pub mod segment;
 pub mod selector;
+pub mod stream;
 pub mod token;
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 pub use errors::JSONPathError;
 pub use errors::JSONPathErrorType;
+pub use extensions::Extensions;
 pub use node::Node;
 pub use node::NodeList;
 pub use parser::JSONPathParser;
@@ -20,11 +25,35 @@ pub use query::Query;
 
 use pyo3::prelude::*;
 
+#[derive(Clone)]
 pub struct QueryContext<'py> {
     env: &'py environment::Env,
     root: Bound<'py, PyAny>,
 }
 
+impl<'py> QueryContext<'py> {
+    /// Host-supplied state bound to this query's `Env` (see `Extensions`),
+    /// for custom selectors and function extensions that need to reach it
+    /// without a parameter threaded through every resolution method.
+    pub fn extensions(&self) -> &Arc<Extensions> {
+        &self.env.extensions
+    }
+
+    /// The maximum depth `Segment::Recursive` may descend to, configured via
+    /// this query's `Env` (see `Env`'s `max_depth` option), or `None` if
+    /// descent is unbounded.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.env.max_depth
+    }
+
+    /// The maximum number of nodes a segment may produce before evaluation
+    /// aborts, configured via this query's `Env` (see `Env`'s `max_results`
+    /// option), or `None` if results are unbounded.
+    pub fn max_results(&self) -> Option<usize> {
+        self.env.max_results
+    }
+}
+
 #[pyclass]
 #[derive(Clone, Copy, Debug)]
 pub enum ExpressionType {
@@ -107,6 +136,10 @@ fn jpq_extension(m: &Bound<'_, PyModule>) -> PyResult<()> {
         "JSONPathExtensionError",
         m.py().get_type_bound::<errors::JSONPathExtensionError>(),
     )?;
+    m.add(
+        "JSONPathLimitError",
+        m.py().get_type_bound::<errors::JSONPathLimitError>(),
+    )?;
     m.add_class::<ExpressionType>()?;
     m.add_class::<segment::Segment>()?;
     m.add_class::<selector::Selector>()?;
@@ -115,6 +148,7 @@ fn jpq_extension(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<filter::FilterExpression>()?;
     m.add_class::<query::Query>()?;
     m.add_class::<environment::Env>()?;
+    m.add_class::<stream::StreamEvaluator>()?;
     m.add_class::<Node>()?;
     Ok(())
 }
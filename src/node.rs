@@ -1,4 +1,4 @@
-use std::{collections::VecDeque, iter};
+use std::iter;
 
 use pyo3::prelude::*;
 use pyo3::{Bound, PyAny, PyObject};
@@ -50,20 +50,206 @@ impl Node {
 
 #[pymethods]
 impl Node {
-    /// The location of this node's value in the query argument as a normalized path.
+    /// The location of this node's value in the query argument as an RFC
+    /// 9535 normalized path, e.g. `$['store']['book'][0]['title']`.
     pub fn path(&self) -> String {
-        iter::once(String::from("$"))
-            .chain(
-                VecDeque::from_iter(self.location.iter().map(|e| match e {
-                    PathElement::Index(i) => format!("[{}]", i),
-                    PathElement::Name(s) => format!("['{}']", s),
-                }))
-                .into_iter()
-                .rev(),
-            )
-            .collect::<Vec<String>>()
-            .join("")
-    }
-
-    // TODO: key()
+        render_path(&self.location)
+    }
+
+    /// The location of this node's value as an RFC 6901 JSON Pointer, e.g.
+    /// `/store/book/0/title`.
+    pub fn to_pointer(&self) -> String {
+        render_pointer(&self.location)
+    }
+
+    /// The array index or object member name that selected this node from
+    /// its parent, or `None` if this node is the query root.
+    pub fn key(&self, py: Python<'_>) -> PyObject {
+        match self.location.iter().next() {
+            Some(PathElement::Index(i)) => i.into_py(py),
+            Some(PathElement::Name(s)) => s.into_py(py),
+            None => py.None(),
+        }
+    }
+
+    /// The normalized path of this node's parent container, e.g.
+    /// `$['store']['book']` for a node at `$['store']['book'][0]`. Returns
+    /// `$` unchanged for the root.
+    pub fn parent_path(&self) -> String {
+        render_path(&parent_location(&self.location))
+    }
+}
+
+/// Build `location`'s elements, root-first, by reversing the cons-list's
+/// natural most-recently-appended-first iteration order.
+fn ordered_elements(location: &Location) -> Vec<PathElement> {
+    let mut elements: Vec<PathElement> = location.iter().cloned().collect();
+    elements.reverse();
+    elements
+}
+
+/// `location` with its trailing (most recently appended) element dropped,
+/// or an empty location if `location` is already empty.
+fn parent_location(location: &Location) -> Location {
+    let mut elements = ordered_elements(location);
+    elements.pop();
+
+    let mut parent = Location::new();
+    for element in elements {
+        parent = parent.append(element);
+    }
+    parent
+}
+
+/// Render `location` as an RFC 9535 normalized path. Exposed beyond this
+/// module so error types elsewhere (see `JSONPathError::with_path`) can name
+/// the node that triggered an evaluation-time limit.
+pub(crate) fn render_path(location: &Location) -> String {
+    iter::once(String::from("$"))
+        .chain(ordered_elements(location).into_iter().map(|e| match e {
+            PathElement::Index(i) => format!("[{}]", i),
+            PathElement::Name(s) => format!("['{}']", escape_normalized_name(&s)),
+        }))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn render_pointer(location: &Location) -> String {
+    iter::once(String::new())
+        .chain(ordered_elements(location).into_iter().map(|e| match e {
+            PathElement::Index(i) => i.to_string(),
+            PathElement::Name(s) => escape_pointer_token(&s),
+        }))
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// Escape an object member name for use as an RFC 6901 JSON Pointer
+/// reference token: `~` becomes `~0` and `/` becomes `~1` (in that order, so
+/// an escaped `~1` isn't re-escaped into `~01`).
+fn escape_pointer_token(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+/// Escape an object member name for use inside the single-quoted bracket
+/// notation of an RFC 9535 normalized path: backslashes and single quotes
+/// are backslash-escaped, control characters use their short escape (or a
+/// `\u00XX` escape when there isn't one), and everything else is passed
+/// through unchanged.
+fn escape_normalized_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\'' => escaped.push_str("\\'"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                escaped.push_str(&format!("\\u{:04x}", c as u32));
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name_location(name: &str) -> Location {
+        Location::new().append(PathElement::Name(name.to_owned()))
+    }
+
+    #[test]
+    fn normalized_path_escapes_backslash_and_quote() {
+        assert_eq!(render_path(&name_location(r#"it's a \test"#)), r#"$['it\'s a \\test']"#);
+    }
+
+    #[test]
+    fn normalized_path_uses_short_escapes_for_common_control_characters() {
+        assert_eq!(
+            render_path(&name_location("a\u{8}b\u{c}c\nd\re\tf")),
+            r"$['a\bb\fc\nd\re\tf']"
+        );
+    }
+
+    #[test]
+    fn normalized_path_falls_back_to_unicode_escape_for_other_control_characters() {
+        assert_eq!(render_path(&name_location("\u{1}")), r"$['\u0001']");
+    }
+
+    #[test]
+    fn normalized_path_passes_non_ascii_through_unescaped() {
+        assert_eq!(render_path(&name_location("café")), "$['café']");
+    }
+
+    #[test]
+    fn json_pointer_escapes_tilde_before_slash_so_it_is_not_reescaped() {
+        // `/` would become `~01` (wrong) if `~` were escaped after `/`.
+        assert_eq!(render_pointer(&name_location("/")), "/~1");
+    }
+
+    #[test]
+    fn json_pointer_escapes_slash_and_tilde_together() {
+        assert_eq!(render_pointer(&name_location("a/b~c")), "/a~1b~0c");
+    }
+
+    fn node_at(py: Python<'_>, location: Location) -> Node {
+        Node {
+            value: py.None(),
+            location,
+        }
+    }
+
+    #[test]
+    fn to_pointer_matches_rfc_6901_for_a_nested_node() {
+        Python::with_gil(|py| {
+            let location = Location::new()
+                .append(PathElement::Name("store".to_owned()))
+                .append(PathElement::Name("book".to_owned()))
+                .append(PathElement::Index(0));
+            assert_eq!(node_at(py, location).to_pointer(), "/store/book/0");
+        });
+    }
+
+    #[test]
+    fn key_is_the_last_path_element() {
+        Python::with_gil(|py| {
+            let location = Location::new()
+                .append(PathElement::Name("store".to_owned()))
+                .append(PathElement::Index(2));
+            let node = node_at(py, location);
+            assert_eq!(node.key(py).extract::<i64>(py).unwrap(), 2);
+        });
+    }
+
+    #[test]
+    fn key_is_none_for_the_root_node() {
+        Python::with_gil(|py| {
+            let node = node_at(py, Location::new());
+            assert!(node.key(py).is_none(py));
+        });
+    }
+
+    #[test]
+    fn parent_path_drops_the_trailing_element() {
+        Python::with_gil(|py| {
+            let location = Location::new()
+                .append(PathElement::Name("store".to_owned()))
+                .append(PathElement::Name("book".to_owned()))
+                .append(PathElement::Index(0));
+            assert_eq!(node_at(py, location).parent_path(), "$['store']['book']");
+        });
+    }
+
+    #[test]
+    fn parent_path_of_the_root_is_the_root() {
+        Python::with_gil(|py| {
+            assert_eq!(node_at(py, Location::new()).parent_path(), "$");
+        });
+    }
 }
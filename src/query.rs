@@ -1,15 +1,19 @@
+use std::cell::RefCell;
 use std::fmt;
+use std::rc::Rc;
 
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 
 use crate::conslist::ConsList;
 use crate::environment::Env;
 use crate::segment::Segment;
 use crate::selector::Selector;
-use crate::{Node, NodeList, QueryContext};
+use crate::{JSONPathError, Node, NodeList, QueryContext};
 
 #[pyclass]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Query {
     #[pyo3(get)]
     pub segments: Vec<Segment>,
@@ -33,7 +37,7 @@ impl Query {
     }
 
     // Apply this query to Python object `value` using the function register from `env`.
-    pub fn resolve(&self, value: &Bound<'_, PyAny>, env: &Env) -> NodeList {
+    pub fn resolve(&self, value: &Bound<'_, PyAny>, env: &Env) -> Result<NodeList, JSONPathError> {
         let root_node = Node {
             value: value.clone().unbind(),
             location: ConsList::new(),
@@ -44,11 +48,72 @@ impl Query {
             root: value.clone(),
         };
 
-        self.segments
-            .iter()
-            .fold(vec![root_node], |nodes, segment| {
-                segment.resolve(nodes, &context)
-            })
+        let mut nodes = vec![root_node];
+        for segment in &self.segments {
+            nodes = segment.resolve(nodes, &context)?;
+        }
+        Ok(nodes)
+    }
+
+    /// Like `resolve`, but without materializing the result: each segment is
+    /// chained lazily (see `Segment::lazy_resolve`), so a caller that only
+    /// pulls the first few items — `first()` below, or a future `take(n)` —
+    /// can stop the document walk as soon as it has enough, instead of
+    /// paying for e.g. a full `$..*` before a later `[0]` narrows it down.
+    ///
+    /// Each segment gets its own clone of the (cheap) `QueryContext` so the
+    /// chain can own what it needs rather than borrowing from this function's
+    /// stack frame. An error from one segment is threaded through as a final
+    /// item once every match already produced downstream of it has been
+    /// yielded, so early results are still usable even if a later node in the
+    /// same query would have failed.
+    pub fn lazy_resolve<'q, 'py>(
+        &'q self,
+        value: &Bound<'py, PyAny>,
+        env: &'py Env,
+    ) -> Box<dyn Iterator<Item = Result<Node, JSONPathError>> + 'q>
+    where
+        'py: 'q,
+    {
+        let root_node = Node {
+            value: value.clone().unbind(),
+            location: ConsList::new(),
+        };
+
+        let context = QueryContext {
+            env,
+            root: value.clone(),
+        };
+
+        let mut nodes: Box<dyn Iterator<Item = Result<Node, JSONPathError>> + 'q> =
+            Box::new(std::iter::once(Ok(root_node)));
+
+        for segment in &self.segments {
+            let error: Rc<RefCell<Option<JSONPathError>>> = Rc::new(RefCell::new(None));
+            let sink = Rc::clone(&error);
+            let input = nodes.map_while(move |item| match item {
+                Ok(node) => Some(node),
+                Err(err) => {
+                    *sink.borrow_mut() = Some(err);
+                    None
+                }
+            });
+
+            let resolved = segment.lazy_resolve(input, context.clone());
+            nodes = Box::new(resolved.chain(std::iter::from_fn(move || error.borrow_mut().take().map(Err))));
+        }
+
+        nodes
+    }
+
+    /// The first node `self` matches against `value`, or `None` if it
+    /// matches nothing — without resolving the rest of the query.
+    pub fn first<'py>(
+        &self,
+        value: &Bound<'py, PyAny>,
+        env: &'py Env,
+    ) -> Result<Option<Node>, JSONPathError> {
+        self.lazy_resolve(value, env).next().transpose()
     }
 
     // Returns `true` if this query has no segments, or `false` otherwise.
@@ -56,6 +121,16 @@ impl Query {
         self.segments.is_empty()
     }
 
+    /// Return a copy of this query with every filter expression's `&&`/`||`
+    /// chains reordered cheapest-first, so short-circuit evaluation (see
+    /// `FilterExpression::evaluate`) does as little work as possible. Used by
+    /// `Env` when its `reorder_filters` option is enabled.
+    pub fn reordered(&self) -> Query {
+        Query {
+            segments: self.segments.iter().map(Segment::reordered).collect(),
+        }
+    }
+
     // Returns `true` if this query can resolve to at most one node, or `false` otherwise.
     pub fn is_singular(&self) -> bool {
         self.segments.iter().all(|segment| {
@@ -79,6 +154,85 @@ impl Query {
     fn __str__(&self) -> String {
         self.to_string()
     }
+
+    /// Structural equality, ignoring source spans — lets compiled queries be
+    /// deduplicated even if (re-)parsed from differently-formatted text.
+    fn __eq__(&self, other: &Query) -> bool {
+        self == other
+    }
+
+    fn __hash__(&self) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(feature = "serde")]
+#[pymethods]
+impl Query {
+    /// Serialize this compiled query to JSON, so it can be cached (on disk,
+    /// in Redis, ...) instead of being re-lexed and re-parsed on every use.
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(self).map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Deserialize a compiled query previously produced by `to_json`.
+    ///
+    /// Function-extension calls are serialized by name only; they're
+    /// resolved against the live function register again the next time the
+    /// query is evaluated, not at deserialization time.
+    #[staticmethod]
+    fn from_json(data: &str) -> PyResult<Query> {
+        serde_json::from_str(data).map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::selector::Selector;
+
+    fn sample_query() -> Query {
+        Query::new(vec![
+            Segment::Child {
+                selectors: vec![Selector::Name { name: "store".to_owned() }],
+            },
+            Segment::Recursive {
+                selectors: vec![Selector::Wild {}],
+            },
+            Segment::Child {
+                selectors: vec![
+                    Selector::Index { index: -1 },
+                    Selector::Slice { start: Some(0), stop: None, step: Some(2) },
+                ],
+            },
+        ])
+    }
+
+    #[test]
+    fn to_json_from_json_round_trips_to_an_equal_query() {
+        let query = sample_query();
+        let json = query.to_json().unwrap();
+        let restored = Query::from_json(&json).unwrap();
+        assert_eq!(query, restored);
+    }
+
+    #[test]
+    fn to_json_from_json_round_trips_to_the_same_display_form() {
+        let query = sample_query();
+        let json = query.to_json().unwrap();
+        let restored = Query::from_json(&json).unwrap();
+        assert_eq!(query.to_string(), restored.to_string());
+    }
+
+    #[test]
+    fn from_json_rejects_malformed_input() {
+        assert!(Query::from_json("not json").is_err());
+    }
 }
 
 impl fmt::Display for Query {
@@ -1,3 +1,4 @@
+use std::collections::{HashSet, VecDeque};
 use std::fmt;
 
 use pyo3::prelude::*;
@@ -5,10 +6,11 @@ use pyo3::types::{PyDict, PyList};
 
 use crate::node::{Location, PathElement, Value};
 use crate::selector::Selector;
-use crate::{NodeList, QueryContext};
+use crate::{JSONPathError, Node, NodeList, QueryContext};
 
 #[pyclass]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Segment {
     Child { selectors: Vec<Selector> },
     Recursive { selectors: Vec<Selector> },
@@ -16,80 +18,308 @@ pub enum Segment {
 }
 
 impl Segment {
-    pub fn resolve(&self, nodes: NodeList, context: &QueryContext) -> NodeList {
+    // Apply this segment to `nodes`, eagerly materializing every match. A
+    // thin wrapper over `lazy_resolve` kept for callers (`Query::resolve`)
+    // that want the whole `NodeList` at once.
+    pub fn resolve(&self, nodes: NodeList, context: &QueryContext) -> Result<NodeList, JSONPathError> {
+        self.lazy_resolve(nodes.into_iter(), context.clone()).collect()
+    }
+
+    /// Apply this segment to `nodes` without materializing the result,
+    /// returning an iterator that produces matches (or the first error) on
+    /// demand. `Child` flat-maps each input node through its selectors;
+    /// `Recursive` drives its cycle-safe, non-recursive work-stack traversal
+    /// one node at a time instead of into a `Vec`, so a consumer that only
+    /// wants the first few matches of e.g. `$..*[0]` can stop walking the
+    /// document as soon as it has them.
+    ///
+    /// `context` is taken by value (it's cheap to `Clone` — a `&Env` and a
+    /// refcounted `Bound`) rather than by reference, so the returned
+    /// iterator can own it and outlive the caller's stack frame; this is
+    /// what lets `Query::lazy_resolve` chain segments together lazily.
+    ///
+    /// The returned iterator is wrapped in `LimitResults`, so it aborts with
+    /// a `JSONPathLimitError`-typed error once it has produced more than
+    /// `context`'s `max_results` nodes (see `Env::max_results`); `Recursive`
+    /// additionally checks `Env::max_depth` against each frame it descends
+    /// into.
+    pub fn lazy_resolve<'ctx, 'py>(
+        &'ctx self,
+        nodes: impl Iterator<Item = Node> + 'ctx,
+        context: QueryContext<'py>,
+    ) -> Box<dyn Iterator<Item = Result<Node, JSONPathError>> + 'ctx>
+    where
+        'py: 'ctx,
+    {
+        let max_results = context.max_results();
+        let resolved: Box<dyn Iterator<Item = Result<Node, JSONPathError>> + 'ctx> = match self {
+            Segment::Child { selectors } => {
+                let mut poisoned = false;
+                Box::new(nodes.flat_map(move |node| {
+                    if poisoned {
+                        return Vec::new().into_iter();
+                    }
+
+                    let mut matches = Vec::new();
+                    for s in selectors {
+                        match s.resolve(node.value.bind(context.root.py()), &node.location, &context)
+                        {
+                            Ok(found) => matches.extend(found.into_iter().map(Ok)),
+                            Err(err) => {
+                                matches.push(Err(err));
+                                poisoned = true;
+                                break;
+                            }
+                        }
+                    }
+                    matches.into_iter()
+                }))
+            }
+            Segment::Recursive { selectors } => {
+                Box::new(RecursiveDescent::new(nodes, selectors, context))
+            }
+            Segment::Eoi {} => Box::new(nodes.map(Ok)),
+        };
+
+        Box::new(LimitResults::new(resolved, max_results))
+    }
+
+    /// Return a copy of this segment with each selector's filter expressions
+    /// reordered cheapest-first (see `FilterExpression::reordered`).
+    pub fn reordered(&self) -> Segment {
         match self {
-            Segment::Child { selectors } => nodes
-                .into_iter()
-                .flat_map(|node| {
-                    selectors.iter().map(move |s| {
-                        s.resolve(node.value.bind(context.root.py()), &node.location, context)
-                    })
-                })
-                .flatten()
-                .collect(),
-            Segment::Recursive { selectors } => nodes
-                .into_iter()
-                .flat_map(move |node| {
-                    self.visit(
-                        node.value.bind(context.root.py()),
-                        node.location,
-                        selectors,
-                        context,
-                    )
-                })
-                .collect(),
-            Segment::Eoi {} => nodes,
+            Segment::Child { selectors } => Segment::Child {
+                selectors: selectors.iter().map(Selector::reordered).collect(),
+            },
+            Segment::Recursive { selectors } => Segment::Recursive {
+                selectors: selectors.iter().map(Selector::reordered).collect(),
+            },
+            Segment::Eoi {} => Segment::Eoi {},
+        }
+    }
+}
+
+/// Given a child reached from a container whose current ancestor-id set
+/// (including its own id, if it's a container) is `parent_ancestors`, return
+/// the ancestor set to use for that child, or `None` if descending into it
+/// would re-enter a container already on the path (a reference cycle).
+fn child_ancestors(child: &Value<'_>, parent_ancestors: &HashSet<usize>) -> Option<HashSet<usize>> {
+    let is_container = child.downcast::<PyList>().is_ok() || child.downcast::<PyDict>().is_ok();
+    if !is_container {
+        return Some(parent_ancestors.clone());
+    }
+
+    let id = child.as_ptr() as usize;
+    if parent_ancestors.contains(&id) {
+        return None;
+    }
+
+    let mut ancestors = parent_ancestors.clone();
+    ancestors.insert(id);
+    Some(ancestors)
+}
+
+/// Wraps any segment-resolution iterator and aborts, with a `JSONPathLimitError`-typed
+/// `JSONPathError`, once more than `limit` nodes have been produced — an
+/// `Env::max_results` backstop against a segment (most expensively `$..`)
+/// materializing an unbounded `NodeList` over an untrusted document. Mirrors
+/// the `poisoned`/`errored` flag used by `Segment::Child` and
+/// `RecursiveDescent` to stop pulling from `inner` once it has yielded one.
+struct LimitResults<I> {
+    inner: I,
+    limit: Option<usize>,
+    count: usize,
+    done: bool,
+}
+
+impl<I> LimitResults<I> {
+    fn new(inner: I, limit: Option<usize>) -> Self {
+        LimitResults {
+            inner,
+            limit,
+            count: 0,
+            done: false,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<Node, JSONPathError>>> Iterator for LimitResults<I> {
+    type Item = Result<Node, JSONPathError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.inner.next() {
+            Some(Ok(node)) => {
+                self.count += 1;
+                if let Some(limit) = self.limit {
+                    if self.count > limit {
+                        self.done = true;
+                        return Some(Err(JSONPathError::limit(format!(
+                            "query exceeded max_results of {}",
+                            limit
+                        ))
+                        .with_path(crate::node::render_path(&node.location))));
+                    }
+                }
+                Some(Ok(node))
+            }
+            Some(Err(err)) => {
+                self.done = true;
+                Some(Err(err))
+            }
+            None => None,
         }
     }
+}
+
+/// One pending value (a container or a scalar) in a `RecursiveDescent`'s
+/// work-stack (see `child_ancestors` for what `ancestors` tracks).
+struct Frame<'py> {
+    value: Value<'py>,
+    location: Location,
+    ancestors: HashSet<usize>,
+    /// How many `Recursive` descents deep this frame is below the root it
+    /// started from, checked against `Env::max_depth` in `child_frame`.
+    depth: usize,
+}
 
-    fn visit(
+/// A lazy `$..` traversal: an external iterator driving a cycle-safe,
+/// ancestor-tracked work-stack descent (see `child_ancestors`), plus a queue of already-computed selector matches for the frame currently
+/// being expanded. Pulling one `Node` at a time means a consumer that stops
+/// early (e.g. after the first match of `$..*[0]`) lets the rest of the
+/// document go unwalked, rather than only skipping the rest of an
+/// already-built `Vec`.
+struct RecursiveDescent<'a, 'py> {
+    roots: Box<dyn Iterator<Item = Node> + 'a>,
+    stack: Vec<Frame<'py>>,
+    pending: VecDeque<Result<Node, JSONPathError>>,
+    selectors: &'a [Selector],
+    context: QueryContext<'py>,
+    errored: bool,
+}
+
+impl<'a, 'py> RecursiveDescent<'a, 'py> {
+    fn new(
+        roots: impl Iterator<Item = Node> + 'a,
+        selectors: &'a [Selector],
+        context: QueryContext<'py>,
+    ) -> Self {
+        RecursiveDescent {
+            roots: Box::new(roots),
+            stack: Vec::new(),
+            pending: VecDeque::new(),
+            selectors,
+            context,
+            errored: false,
+        }
+    }
+
+    fn push_root(&mut self, node: Node) {
+        let value = node.value.bind(self.context.root.py()).clone();
+        let mut ancestors = HashSet::new();
+        if value.downcast::<PyList>().is_ok() || value.downcast::<PyDict>().is_ok() {
+            ancestors.insert(value.as_ptr() as usize);
+        }
+        self.stack.push(Frame {
+            value,
+            location: node.location,
+            ancestors,
+            depth: 0,
+        });
+    }
+
+    /// Build the `Frame` for descending into `value` at `location`, or an
+    /// `Err` if doing so would put it deeper than `Env::max_depth` allows.
+    fn child_frame(
         &self,
-        value: &Value<'_>,
+        value: Value<'py>,
         location: Location,
-        selectors: &Vec<Selector>,
-        context: &QueryContext,
-    ) -> NodeList {
-        let mut nodes: NodeList = selectors
-            .iter()
-            .flat_map(|s| s.resolve(value, &location, context))
-            .collect();
-
-        nodes.append(&mut self.descend(value, &location, selectors, context));
-        nodes
+        ancestors: HashSet<usize>,
+        depth: usize,
+    ) -> Result<Frame<'py>, JSONPathError> {
+        if let Some(limit) = self.context.max_depth() {
+            if depth > limit {
+                return Err(JSONPathError::limit(format!(
+                    "recursive descent exceeded max_depth of {}",
+                    limit
+                ))
+                .with_path(crate::node::render_path(&location)));
+            }
+        }
+
+        Ok(Frame {
+            value,
+            location,
+            ancestors,
+            depth,
+        })
     }
 
-    fn descend(
-        &self,
-        value: &Value<'_>,
-        location: &Location,
-        selectors: &Vec<Selector>,
-        context: &QueryContext,
-    ) -> NodeList {
-        if let Ok(list) = value.downcast::<PyList>() {
-            list.iter()
-                .enumerate()
-                .flat_map(|(i, v)| {
-                    self.visit(
-                        &v,
-                        location.append(PathElement::Index(i)),
-                        selectors,
-                        context,
-                    )
-                })
-                .collect()
-        } else if let Ok(dict) = value.downcast::<PyDict>() {
-            dict.iter()
-                .flat_map(|(k, v)| {
-                    self.visit(
-                        &v,
-                        location.append(PathElement::Name(k.extract().unwrap())),
-                        selectors,
-                        context,
-                    )
-                })
-                .collect()
-        } else {
-            vec![]
+    fn expand_next_frame(&mut self) -> Result<bool, JSONPathError> {
+        let Some(frame) = self.stack.pop() else {
+            return Ok(false);
+        };
+
+        for s in self.selectors {
+            for node in s.resolve(&frame.value, &frame.location, &self.context)? {
+                self.pending.push_back(Ok(node));
+            }
+        }
+
+        if let Ok(list) = frame.value.downcast::<PyList>() {
+            for (i, v) in list.iter().enumerate().rev() {
+                if let Some(ancestors) = child_ancestors(&v, &frame.ancestors) {
+                    let location = frame.location.append(PathElement::Index(i));
+                    self.stack
+                        .push(self.child_frame(v, location, ancestors, frame.depth + 1)?);
+                }
+            }
+        } else if let Ok(dict) = frame.value.downcast::<PyDict>() {
+            let items: Vec<_> = dict.iter().collect();
+            for (k, v) in items.into_iter().rev() {
+                if let Some(ancestors) = child_ancestors(&v, &frame.ancestors) {
+                    let location = frame.location.append(PathElement::Name(k.extract().unwrap()));
+                    self.stack
+                        .push(self.child_frame(v, location, ancestors, frame.depth + 1)?);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl<'a, 'py> Iterator for RecursiveDescent<'a, 'py> {
+    type Item = Result<Node, JSONPathError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.errored {
+            return None;
+        }
+
+        loop {
+            if let Some(next) = self.pending.pop_front() {
+                return Some(next);
+            }
+
+            if self.stack.is_empty() {
+                match self.roots.next() {
+                    Some(node) => self.push_root(node),
+                    None => return None,
+                }
+                continue;
+            }
+
+            match self.expand_next_frame() {
+                Ok(_) => continue,
+                Err(err) => {
+                    self.errored = true;
+                    return Some(Err(err));
+                }
+            }
         }
     }
 }
@@ -1,15 +1,16 @@
-use std::cmp;
 use std::fmt::{self, Write};
 
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
 
+use crate::backend::{norm_index, slice_bounds};
 use crate::filter::{is_truthy, FilterExpression};
 use crate::node::{Location, Value};
-use crate::{Node, NodeList, QueryContext};
+use crate::{JSONPathError, Node, NodeList, QueryContext};
 
 #[pyclass]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Selector {
     Name {
         name: String,
@@ -34,8 +35,8 @@ impl Selector {
         value: &Value<'py>,
         location: &Location,
         context: &QueryContext,
-    ) -> NodeList {
-        match self {
+    ) -> Result<NodeList, JSONPathError> {
+        let nodes = match self {
             Selector::Name { name, .. } => {
                 if let Ok(v) = value.get_item(name) {
                     vec![Node::new_object_member(v, location, name.to_owned())]
@@ -84,33 +85,40 @@ impl Selector {
             }
             Selector::Filter { expression, .. } => {
                 if let Ok(list) = value.downcast::<PyList>() {
-                    list.iter()
-                        .enumerate()
-                        .map(|(i, v)| (i, v.clone(), expression.evaluate(&v, context)))
-                        .filter(|(_, _, r)| is_truthy(r))
-                        .map(|(i, v, _)| Node::new_array_element(v.clone(), location, i))
-                        .collect()
+                    let mut nodes = Vec::new();
+                    for (i, v) in list.iter().enumerate() {
+                        if is_truthy(&expression.evaluate(&v, context)?) {
+                            nodes.push(Node::new_array_element(v, location, i));
+                        }
+                    }
+                    nodes
                 } else if let Ok(dict) = value.downcast::<PyDict>() {
-                    dict.iter()
-                        .map(|(k, v)| (k, v.clone(), expression.evaluate(&v, context)))
-                        .filter(|(_, _, r)| is_truthy(r))
-                        .map(|(k, v, _)| {
-                            Node::new_object_member(v.clone(), location, k.extract().unwrap())
-                        })
-                        .collect()
+                    let mut nodes = Vec::new();
+                    for (k, v) in dict.iter() {
+                        if is_truthy(&expression.evaluate(&v, context)?) {
+                            nodes.push(Node::new_object_member(v, location, k.extract().unwrap()));
+                        }
+                    }
+                    nodes
                 } else {
                     Vec::new()
                 }
             }
-        }
+        };
+
+        Ok(nodes)
     }
-}
 
-fn norm_index(index: i64, length: usize) -> usize {
-    if index < 0 && length >= index.abs() as usize {
-        (length as i64 + index) as usize
-    } else {
-        index as usize
+    /// Return a copy of this selector with any filter expression's `&&`/`||`
+    /// operands reordered cheapest-first. Other selector variants are
+    /// returned unchanged.
+    pub fn reordered(&self) -> Selector {
+        match self {
+            Selector::Filter { expression } => Selector::Filter {
+                expression: Box::new(expression.reordered()),
+            },
+            other => other.clone(),
+        }
     }
 }
 
@@ -120,67 +128,11 @@ fn slice<'py>(
     stop: Option<i64>,
     step: Option<i64>,
 ) -> Vec<(i64, Bound<'py, PyAny>)> {
-    let array_length = list.len() as i64; // TODO: try_from
-    if array_length == 0 {
-        return Vec::new();
-    }
-
-    let n_step = step.unwrap_or(1);
-
-    if n_step == 0 {
-        return Vec::new();
-    }
-
-    let n_start = match start {
-        Some(i) => {
-            if i < 0 {
-                cmp::max(array_length + i, 0)
-            } else {
-                cmp::min(i, array_length - 1)
-            }
-        }
-        None => {
-            if n_step < 0 {
-                array_length - 1
-            } else {
-                0
-            }
-        }
-    };
-
-    let n_stop = match stop {
-        Some(i) => {
-            if i < 0 {
-                cmp::max(array_length + i, -1)
-            } else {
-                cmp::min(i, array_length)
-            }
-        }
-        None => {
-            if n_step < 0 {
-                -1
-            } else {
-                array_length
-            }
-        }
-    };
-
-    let mut sliced_array: Vec<(i64, Bound<'py, PyAny>)> = Vec::new();
-
-    // TODO: try_from instead of as
-    if n_step > 0 {
-        for i in (n_start..n_stop).step_by(n_step as usize) {
-            sliced_array.push((i, list.get_item(i as usize).unwrap()));
-        }
-    } else {
-        let mut i = n_start;
-        while i > n_stop {
-            sliced_array.push((i, list.get_item(i as usize).unwrap()));
-            i += n_step;
-        }
-    }
-
-    sliced_array
+    slice_bounds(list.len(), start, stop, step)
+        .indices()
+        .into_iter()
+        .map(|i| (i, list.get_item(i as usize).unwrap()))
+        .collect()
 }
 
 #[pymethods]
@@ -0,0 +1,591 @@
+//! Incremental evaluation over a stream of JSON parse events, so large
+//! documents can be queried without first materializing the whole thing as
+//! Python objects.
+//!
+//! A [`StreamEvaluator`] is fed one parse event at a time (as a real
+//! streaming JSON tokenizer would emit them), through the `feed_*` method
+//! matching each [`JsonEvent`] variant, and maintains, per query
+//! segment, the set of "in progress" matches that still have a chance of
+//! completing. Matching `Selector::Name`/`Index`/`Wild` only needs the
+//! current key/index, so those advance on `ObjectKey`/array-index events
+//! alone; a `Selector::Filter` or a `Segment::Recursive` needs to see an
+//! entire subtree before it can decide, so those aren't supported here yet
+//! and are rejected up front in `StreamEvaluator::new`. A match on an
+//! object/array (rather than a scalar) is captured event-by-event into a
+//! [`CapturedValue`] as its subtree streams past, then converted to a Python
+//! object only once its closing event arrives.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::query::Query;
+use crate::segment::Segment;
+use crate::selector::Selector;
+use crate::{JSONPathError, JSONPathErrorType};
+
+/// One token from a streaming JSON parser.
+#[derive(Debug, Clone)]
+pub enum JsonEvent {
+    StartObject,
+    ObjectKey(String),
+    StartArray,
+    Value(JsonScalar),
+    EndArray,
+    EndObject,
+}
+
+/// A scalar JSON value, as carried by a `JsonEvent::Value`.
+#[derive(Debug, Clone)]
+pub enum JsonScalar {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl IntoPy<PyObject> for JsonScalar {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        match self {
+            JsonScalar::Null => py.None(),
+            JsonScalar::Bool(b) => b.into_py(py),
+            JsonScalar::Int(i) => i.into_py(py),
+            JsonScalar::Float(f) => f.into_py(py),
+            JsonScalar::String(s) => s.into_py(py),
+        }
+    }
+}
+
+/// One frame of the container nesting a `StreamEvaluator` is currently
+/// inside (tracked for every `Start*`/`End*` pair, regardless of whether it
+/// carries any live partial matches).
+#[derive(Debug, Clone)]
+enum Frame {
+    Object,
+    Array { next_index: usize },
+}
+
+/// A query segment index that a path, as seen so far, still satisfies.
+#[derive(Debug, Clone, Copy)]
+struct PartialMatch {
+    segment: usize,
+}
+
+/// A JSON value built up one event at a time while a matched object/array's
+/// subtree streams past, so it can be emitted as a single Python object once
+/// its closing event is seen.
+#[derive(Debug, Clone)]
+enum CapturedValue {
+    Scalar(JsonScalar),
+    Object(Vec<(String, CapturedValue)>),
+    Array(Vec<CapturedValue>),
+}
+
+impl IntoPy<PyObject> for CapturedValue {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        match self {
+            CapturedValue::Scalar(value) => value.into_py(py),
+            CapturedValue::Object(fields) => {
+                let dict = PyDict::new_bound(py);
+                for (key, value) in fields {
+                    dict.set_item(key, value.into_py(py)).unwrap();
+                }
+                dict.into_py(py)
+            }
+            CapturedValue::Array(items) => {
+                let values: Vec<PyObject> = items.into_iter().map(|v| v.into_py(py)).collect();
+                PyList::new_bound(py, values).into_py(py)
+            }
+        }
+    }
+}
+
+/// One container, still being filled in, inside an in-progress capture.
+#[derive(Debug, Clone)]
+struct OpenCapture {
+    /// The key this container will be inserted under once it closes and its
+    /// parent is an object, or `None` if the parent is an array (or this is
+    /// the captured root, which has no parent to attach to).
+    key: Option<String>,
+    fields: Vec<(String, CapturedValue)>,
+    items: Vec<CapturedValue>,
+    is_object: bool,
+}
+
+impl OpenCapture {
+    fn new(is_object: bool, key: Option<String>) -> Self {
+        OpenCapture { key, fields: Vec::new(), items: Vec::new(), is_object }
+    }
+
+    fn finish(self) -> CapturedValue {
+        if self.is_object {
+            CapturedValue::Object(self.fields)
+        } else {
+            CapturedValue::Array(self.items)
+        }
+    }
+
+    fn push_scalar(&mut self, key: Option<String>, value: JsonScalar) {
+        self.push(key, CapturedValue::Scalar(value));
+    }
+
+    fn push(&mut self, key: Option<String>, value: CapturedValue) {
+        if self.is_object {
+            if let Some(key) = key {
+                self.fields.push((key, value));
+            }
+        } else {
+            self.items.push(value);
+        }
+    }
+}
+
+/// An object/array match that fully satisfied the query (reached
+/// `Segment::Eoi`) before its value was seen, being built from the events of
+/// its own subtree until the matching closing event completes it.
+#[derive(Debug, Clone)]
+struct Capture {
+    /// Root-first stack of containers currently open within the captured
+    /// subtree; `open.last()` is the container presently being fed.
+    open: Vec<OpenCapture>,
+    /// The `ObjectKey` most recently seen while capturing, not yet paired
+    /// with the value (scalar or container) that follows it.
+    pending_key: Option<String>,
+}
+
+/// Incrementally evaluates a single [`Query`] against a stream of
+/// [`JsonEvent`]s, without ever materializing the whole document.
+#[pyclass]
+pub struct StreamEvaluator {
+    query: Query,
+    stack: Vec<Frame>,
+    /// Whether `enter` pushed a new `frontier` level for the matching
+    /// `stack` entry (it doesn't for the document root, which has no key of
+    /// its own and is already covered by `frontier[0]`).
+    frontier_pushed: Vec<bool>,
+    /// `frontier.last()`: the partial matches that apply to *every child* of
+    /// the container currently being visited. Stays fixed while sibling
+    /// keys/indices are tested against it, and is only replaced by entering
+    /// or leaving a container.
+    frontier: Vec<Vec<PartialMatch>>,
+    /// The matches computed for whichever key/index was most recently seen,
+    /// consumed by the next container entered or scalar value completed.
+    pending: Option<Vec<PartialMatch>>,
+    /// An in-progress capture of a matched object/array, or `None` when no
+    /// such match is currently being built.
+    capture: Option<Capture>,
+    ready: Vec<PyObject>,
+}
+
+#[pymethods]
+impl StreamEvaluator {
+    #[new]
+    pub fn new(query: Query) -> Result<Self, JSONPathError> {
+        for segment in &query.segments {
+            match segment {
+                Segment::Recursive { .. } => {
+                    return Err(JSONPathError::new(
+                        JSONPathErrorType::ExtError,
+                        "streaming evaluation does not support recursive descent segments"
+                            .to_owned(),
+                    ))
+                }
+                Segment::Child { selectors } => {
+                    for selector in selectors {
+                        match selector {
+                            Selector::Filter { .. } => {
+                                return Err(JSONPathError::new(
+                                    JSONPathErrorType::ExtError,
+                                    "streaming evaluation does not support filter selectors"
+                                        .to_owned(),
+                                ))
+                            }
+                            Selector::Index { index } if *index < 0 => {
+                                return Err(JSONPathError::new(
+                                    JSONPathErrorType::ExtError,
+                                    "streaming evaluation does not support negative array indices"
+                                        .to_owned(),
+                                ))
+                            }
+                            Selector::Slice { start, stop, step } => {
+                                let forward = step.map_or(true, |step| step > 0)
+                                    && start.map_or(true, |start| start >= 0)
+                                    && stop.map_or(true, |stop| stop >= 0);
+                                if !forward {
+                                    return Err(JSONPathError::new(
+                                        JSONPathErrorType::ExtError,
+                                        "streaming evaluation only supports forward slices \
+                                         (non-negative start/stop, positive step)"
+                                            .to_owned(),
+                                    ));
+                                }
+                            }
+                            Selector::Name { .. } | Selector::Index { .. } | Selector::Wild {} => {}
+                        }
+                    }
+                }
+                Segment::Eoi {} => {}
+            }
+        }
+
+        Ok(StreamEvaluator {
+            query,
+            stack: Vec::new(),
+            frontier_pushed: Vec::new(),
+            frontier: vec![vec![PartialMatch { segment: 0 }]],
+            pending: None,
+            capture: None,
+            ready: Vec::new(),
+        })
+    }
+
+    pub fn feed_start_object(&mut self) {
+        self.sync_array_index();
+        self.enter(Frame::Object);
+    }
+
+    pub fn feed_start_array(&mut self) {
+        self.sync_array_index();
+        self.enter(Frame::Array { next_index: 0 });
+    }
+
+    pub fn feed_object_key(&mut self, key: &str) {
+        self.pending = Some(self.match_key(Key::Name(key)));
+        if let Some(capture) = &mut self.capture {
+            capture.pending_key = Some(key.to_owned());
+        }
+    }
+
+    pub fn feed_value(&mut self, value: JsonScalar, py: Python<'_>) {
+        self.sync_array_index();
+        self.complete_if_matched(value.clone(), py);
+        self.record_captured_scalar(value);
+        self.advance_array_index();
+    }
+
+    pub fn feed_end_array(&mut self, py: Python<'_>) {
+        self.exit(py);
+    }
+
+    pub fn feed_end_object(&mut self, py: Python<'_>) {
+        self.exit(py);
+    }
+
+    /// Drain the values matched since the last call to this method.
+    pub fn take_ready(&mut self) -> Vec<PyObject> {
+        std::mem::take(&mut self.ready)
+    }
+}
+
+enum Key<'a> {
+    Name(&'a str),
+    Index(usize),
+}
+
+impl StreamEvaluator {
+    fn current_array_index(&self) -> Option<usize> {
+        match self.stack.last() {
+            Some(Frame::Array { next_index }) => Some(*next_index),
+            _ => None,
+        }
+    }
+
+    /// If we're directly inside an array, array elements have no preceding
+    /// `ObjectKey` event, so compute `pending` from the current index now.
+    fn sync_array_index(&mut self) {
+        if let Some(index) = self.current_array_index() {
+            self.pending = Some(self.match_key(Key::Index(index)));
+        }
+    }
+
+    /// An array element (scalar or just-closed container) has been fully
+    /// consumed, so the array's next child, if any, is one index further on.
+    fn advance_array_index(&mut self) {
+        if let Some(Frame::Array { next_index }) = self.stack.last_mut() {
+            *next_index += 1;
+        }
+    }
+
+    fn is_eoi_match(&self, candidate: &PartialMatch) -> bool {
+        matches!(self.query.segments.get(candidate.segment), Some(Segment::Eoi {}))
+    }
+
+    fn enter(&mut self, frame: Frame) {
+        match self.pending.take() {
+            Some(matches) => {
+                if self.capture.is_none() && matches.iter().any(|m| self.is_eoi_match(m)) {
+                    self.capture = Some(Capture { open: Vec::new(), pending_key: None });
+                }
+
+                if let Some(capture) = &mut self.capture {
+                    let key = capture.pending_key.take();
+                    capture
+                        .open
+                        .push(OpenCapture::new(matches!(frame, Frame::Object), key));
+                }
+
+                self.frontier.push(matches);
+                self.frontier_pushed.push(true);
+            }
+            // The document root: frontier[0] already covers its children.
+            None => self.frontier_pushed.push(false),
+        }
+        self.stack.push(frame);
+    }
+
+    fn exit(&mut self, py: Python<'_>) {
+        self.stack.pop();
+        if self.frontier_pushed.pop().unwrap_or(false) {
+            self.frontier.pop();
+        }
+        self.finish_capture_container(py);
+        self.advance_array_index();
+    }
+
+    fn match_key(&self, key: Key) -> Vec<PartialMatch> {
+        let Some(candidates) = self.frontier.last() else {
+            return Vec::new();
+        };
+
+        let mut next = Vec::new();
+        for candidate in candidates {
+            let Some(Segment::Child { selectors }) = self.query.segments.get(candidate.segment)
+            else {
+                continue;
+            };
+
+            for selector in selectors {
+                let matched = match (&key, selector) {
+                    (Key::Name(name), Selector::Name { name: sel_name }) => *name == sel_name,
+                    (_, Selector::Wild {}) => true,
+                    (Key::Index(i), Selector::Index { index }) => {
+                        // A fully general fix-up of negative indices needs
+                        // the array length, which isn't known mid-stream;
+                        // only non-negative indices can be matched here
+                        // (negative ones are rejected up front in `new`).
+                        *index >= 0 && *index as usize == *i
+                    }
+                    (Key::Index(i), Selector::Slice { start, stop, step }) => {
+                        // As with `Index`, only slices whose bounds don't
+                        // depend on the (not yet known) array length are
+                        // supported (enforced in `new`): a non-negative
+                        // start, a non-negative stop (or none, meaning "to
+                        // the end"), and a positive step.
+                        let start = start.unwrap_or(0) as usize;
+                        let step = step.unwrap_or(1) as usize;
+                        let before_stop = stop.map_or(true, |stop| (*i as i64) < stop);
+                        *i >= start && before_stop && (*i - start) % step == 0
+                    }
+                    _ => false,
+                };
+
+                if matched {
+                    next.push(PartialMatch {
+                        segment: candidate.segment + 1,
+                    });
+                }
+            }
+        }
+
+        next
+    }
+
+    fn complete_if_matched(&mut self, value: JsonScalar, py: Python<'_>) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+
+        for candidate in &pending {
+            if self.is_eoi_match(candidate) {
+                self.ready.push(value.clone().into_py(py));
+            }
+        }
+    }
+
+    /// If a capture is in progress, append `value` to whichever container is
+    /// currently being fed, under the key `feed_object_key` most recently
+    /// recorded (if its parent is an object).
+    fn record_captured_scalar(&mut self, value: JsonScalar) {
+        let Some(capture) = &mut self.capture else { return };
+        let key = capture.pending_key.take();
+        if let Some(open) = capture.open.last_mut() {
+            open.push_scalar(key, value);
+        }
+    }
+
+    /// If a capture is in progress, close off whichever container the
+    /// matching `Start*` event opened. Once the captured root itself closes,
+    /// convert the finished value to a Python object and queue it.
+    fn finish_capture_container(&mut self, py: Python<'_>) {
+        let Some(capture) = &mut self.capture else { return };
+        let Some(closed) = capture.open.pop() else { return };
+        let key = closed.key.clone();
+        let value = closed.finish();
+
+        match capture.open.last_mut() {
+            Some(parent) => parent.push(key, value),
+            None => {
+                self.capture = None;
+                self.ready.push(value.into_py(py));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(selectors: Vec<Selector>) -> Query {
+        Query::new(vec![Segment::Child { selectors }, Segment::Eoi {}])
+    }
+
+    fn ready_ints(evaluator: &mut StreamEvaluator, py: Python<'_>) -> Vec<i64> {
+        evaluator
+            .take_ready()
+            .into_iter()
+            .map(|obj| obj.extract::<i64>(py).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn array_index_advances_across_scalar_elements() {
+        Python::with_gil(|py| {
+            let mut evaluator =
+                StreamEvaluator::new(query(vec![Selector::Index { index: 1 }])).unwrap();
+
+            evaluator.feed_start_array();
+            evaluator.feed_value(JsonScalar::Int(1), py);
+            evaluator.feed_value(JsonScalar::Int(2), py);
+            evaluator.feed_value(JsonScalar::Int(3), py);
+            evaluator.feed_end_array(py);
+
+            assert_eq!(ready_ints(&mut evaluator, py), vec![2]);
+        });
+    }
+
+    #[test]
+    fn array_index_is_not_corrupted_by_a_mixed_array() {
+        Python::with_gil(|py| {
+            let mut evaluator =
+                StreamEvaluator::new(query(vec![Selector::Index { index: 2 }])).unwrap();
+
+            evaluator.feed_start_array();
+            evaluator.feed_value(JsonScalar::Int(1), py); // index 0
+            evaluator.feed_start_object(); // index 1
+            evaluator.feed_end_object(py);
+            evaluator.feed_value(JsonScalar::Int(3), py); // index 2
+            evaluator.feed_end_array(py);
+
+            assert_eq!(ready_ints(&mut evaluator, py), vec![3]);
+        });
+    }
+
+    #[test]
+    fn wild_matches_every_scalar_array_element() {
+        Python::with_gil(|py| {
+            let mut evaluator = StreamEvaluator::new(query(vec![Selector::Wild {}])).unwrap();
+
+            evaluator.feed_start_array();
+            evaluator.feed_value(JsonScalar::Int(1), py);
+            evaluator.feed_value(JsonScalar::Int(2), py);
+            evaluator.feed_value(JsonScalar::Int(3), py);
+            evaluator.feed_end_array(py);
+
+            assert_eq!(ready_ints(&mut evaluator, py), vec![1, 2, 3]);
+        });
+    }
+
+    #[test]
+    fn a_matched_object_is_captured_and_emitted_whole() {
+        Python::with_gil(|py| {
+            let mut evaluator = StreamEvaluator::new(query(vec![Selector::Name {
+                name: "a".to_owned(),
+            }]))
+            .unwrap();
+
+            evaluator.feed_start_object();
+            evaluator.feed_object_key("a");
+            evaluator.feed_start_object();
+            evaluator.feed_object_key("b");
+            evaluator.feed_value(JsonScalar::Int(1), py);
+            evaluator.feed_end_object(py);
+            evaluator.feed_end_object(py);
+
+            let ready = evaluator.take_ready();
+            assert_eq!(ready.len(), 1);
+            let dict = ready[0].bind(py).downcast::<PyDict>().unwrap();
+            assert_eq!(dict.get_item("b").unwrap().unwrap().extract::<i64>().unwrap(), 1);
+        });
+    }
+
+    #[test]
+    fn a_matched_array_is_captured_and_emitted_whole() {
+        Python::with_gil(|py| {
+            let mut evaluator = StreamEvaluator::new(query(vec![Selector::Name {
+                name: "a".to_owned(),
+            }]))
+            .unwrap();
+
+            evaluator.feed_start_object();
+            evaluator.feed_object_key("a");
+            evaluator.feed_start_array();
+            evaluator.feed_value(JsonScalar::Int(1), py);
+            evaluator.feed_value(JsonScalar::Int(2), py);
+            evaluator.feed_end_array(py);
+            evaluator.feed_end_object(py);
+
+            let ready = evaluator.take_ready();
+            assert_eq!(ready.len(), 1);
+            assert_eq!(ready_ints(&mut evaluator, py), Vec::<i64>::new());
+            let list = ready[0].bind(py).downcast::<PyList>().unwrap();
+            assert_eq!(list.len(), 2);
+        });
+    }
+
+    #[test]
+    fn non_negative_slice_matches_the_indices_it_covers() {
+        Python::with_gil(|py| {
+            let mut evaluator = StreamEvaluator::new(query(vec![Selector::Slice {
+                start: Some(1),
+                stop: None,
+                step: Some(2),
+            }]))
+            .unwrap();
+
+            evaluator.feed_start_array();
+            for i in 0..5 {
+                evaluator.feed_value(JsonScalar::Int(i), py);
+            }
+            evaluator.feed_end_array(py);
+
+            assert_eq!(ready_ints(&mut evaluator, py), vec![1, 3]);
+        });
+    }
+
+    #[test]
+    fn new_rejects_a_negative_index() {
+        let result = StreamEvaluator::new(query(vec![Selector::Index { index: -1 }]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn new_rejects_a_backward_slice() {
+        let result = StreamEvaluator::new(query(vec![Selector::Slice {
+            start: None,
+            stop: None,
+            step: Some(-1),
+        }]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn new_rejects_a_filter_selector() {
+        use crate::filter::FilterExpression;
+
+        let result = StreamEvaluator::new(query(vec![Selector::Filter {
+            expression: Box::new(FilterExpression::True_ { span: (0, 0) }),
+        }]));
+        assert!(result.is_err());
+    }
+}
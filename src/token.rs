@@ -3,6 +3,7 @@ use core::fmt;
 pub const EOQ: char = '\0';
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenType {
     Eoq,
     Error { msg: Box<str> },
@@ -94,6 +95,7 @@ impl fmt::Display for TokenType {
 
 /// A JSONPath expression token, as produced by the lexer.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub kind: TokenType,
     pub span: (usize, usize),